@@ -4,34 +4,45 @@ use thiserror::Error;
 use toml_edit::{Document, Formatted, Item, Value};
 
 #[derive(Debug, PartialEq)]
-struct Dependency<'a> {
-    name: String,
-    version: Version<'a>,
+pub(crate) struct Dependency<'a> {
+    pub(crate) name: String,
+    pub(crate) version: Version<'a>,
 }
 
 #[derive(Debug, PartialEq)]
-struct Version<'a> {
+pub(crate) struct Version<'a> {
     value: &'a mut Formatted<String>,
     version: semver::Version,
+    requirement: String,
     changed: bool,
 }
 
 impl<'a> Version<'a> {
     fn new(value: &'a mut Formatted<String>) -> Result<Self, semver::Error> {
+        let requirement = value.value().to_owned();
         Ok(Self {
             version: semver::Version::from_str(value.value())?,
             value,
+            requirement,
             changed: false,
         })
     }
 }
 
 impl Version<'_> {
-    fn get(&self) -> &semver::Version {
+    pub(crate) fn get(&self) -> &semver::Version {
         &self.version
     }
 
-    fn set(&mut self, version: semver::Version) {
+    /// The literal requirement string as written in `Cargo.toml`, e.g.
+    /// `"1.2.3"`, before it was parsed as an exact version. Only bare exact
+    /// versions get a `Version` (see `new_dependency`), so ranges like
+    /// `"~1.2"` never show up here.
+    pub(crate) fn requirement(&self) -> &str {
+        &self.requirement
+    }
+
+    pub(crate) fn set(&mut self, version: semver::Version) {
         if version != self.version {
             self.changed = true;
             self.version = version;
@@ -47,18 +58,35 @@ impl Drop for Version<'_> {
     }
 }
 
-enum DependencyType {
+#[derive(Debug, Clone)]
+pub(crate) enum DependencyType {
     Standard,
     Dev,
+    Build,
+    Workspace,
+    Target { cfg: String },
 }
 
 impl DependencyType {
     const STANDARD: &str = "dependencies";
     const DEV: &str = "dev-dependencies";
+    const BUILD: &str = "build-dependencies";
+
+    /// The path of the dependency group within the document, e.g.
+    /// `["target", "cfg(unix)", "dependencies"]`.
+    fn path(&self) -> Vec<&str> {
+        match self {
+            DependencyType::Standard => vec![Self::STANDARD],
+            DependencyType::Dev => vec![Self::DEV],
+            DependencyType::Build => vec![Self::BUILD],
+            DependencyType::Workspace => vec!["workspace", Self::STANDARD],
+            DependencyType::Target { cfg } => vec!["target", cfg.as_str(), Self::STANDARD],
+        }
+    }
 }
 
 #[derive(Debug, Error)]
-enum FetchDependenciesError {
+pub(crate) enum FetchDependenciesError {
     #[error("missing dependency group")]
     MissingDependencyItem,
     #[error("unexpected dependencies type \"{ty}\"")]
@@ -67,12 +95,6 @@ enum FetchDependenciesError {
     UnexpectedDependencyItem { key: String, ty: &'static str },
     #[error("unexpected version type \"{ty}\" for \"{key}\"")]
     UnexpectedVersionType { key: String, ty: &'static str },
-    #[error("failed to parse dependency \"{key}\"")]
-    SemverParse {
-        key: String,
-        #[source]
-        error: semver::Error,
-    },
     #[error("missing version key in dependency \"{key}\"")]
     MissingVersionKey { key: String },
 }
@@ -98,55 +120,83 @@ fn item_to_type(item: &Item) -> &'static str {
     }
 }
 
-fn fetch_dependencies(
+/// Walks `path` through nested tables in `document`, returning the table at
+/// the end of the path.
+fn walk_table<'a>(
+    document: &'a mut Document,
+    path: &[&str],
+) -> Result<&'a mut toml_edit::Table, FetchDependenciesError> {
+    use FetchDependenciesError::*;
+
+    let mut table: &mut toml_edit::Table = document;
+    for segment in path {
+        let item = table.get_mut(segment).ok_or(MissingDependencyItem)?;
+        table = match item {
+            Item::Table(table) => table,
+            other => {
+                return Err(UnexpectedDependenciesItem {
+                    ty: item_to_type(other),
+                })
+            }
+        };
+    }
+    Ok(table)
+}
+
+/// Keys that mark a dependency as having no semver to minimize: a git or
+/// path source, or a `{ workspace = true }` inheritance marker.
+const NO_VERSION_SOURCE_KEYS: [&str; 3] = ["git", "path", "workspace"];
+
+/// Whether a dependency table has no `version` key but does have one of
+/// [`NO_VERSION_SOURCE_KEYS`], i.e. it has nothing for us to minimize.
+/// Takes a closure rather than the table itself so it works for both
+/// `toml_edit::Table` and `toml_edit::InlineTable`.
+fn is_sourced_without_version(contains_key: impl Fn(&str) -> bool) -> bool {
+    !contains_key("version") && NO_VERSION_SOURCE_KEYS.iter().any(|key| contains_key(key))
+}
+
+/// Builds a [`Dependency`] from its literal version string, or `None` if
+/// that string isn't a bare exact version (e.g. a range like `"^1.2"`, a
+/// partial version like `"1"`, or garbage). There's nothing for us to
+/// minimize in that case, since we can only zero out an exact version.
+fn new_dependency(name: String, value: &mut Formatted<String>) -> Option<Dependency<'_>> {
+    match Version::new(value) {
+        Ok(version) => Some(Dependency { name, version }),
+        Err(_) => {
+            tracing::debug!(
+                name,
+                "Skipping dependency with a non-exact version requirement"
+            );
+            None
+        }
+    }
+}
+
+pub(crate) fn fetch_dependencies(
     document: &mut Document,
     ty: DependencyType,
 ) -> Result<Vec<Dependency<'_>>, FetchDependenciesError> {
     use FetchDependenciesError::*;
 
-    let dependency_group = match ty {
-        DependencyType::Standard => DependencyType::STANDARD,
-        DependencyType::Dev => DependencyType::DEV,
-    };
-
-    let dependencies = document
-        .get_mut(dependency_group)
-        .ok_or(MissingDependencyItem)?;
-
-    let dependencies = match dependencies {
-        Item::Table(table) => table,
-        other => {
-            return Err(UnexpectedDependenciesItem {
-                ty: item_to_type(other),
-            })
-        }
-    };
+    let dependencies = walk_table(document, &ty.path())?;
 
-    let depedencies: Result<Vec<Dependency>, _> = dependencies
+    let depedencies: Result<Vec<Option<Dependency>>, _> = dependencies
         .iter_mut()
         .map(move |(key, item)| {
             let key = key.get().to_string();
             match item {
                 Item::Value(value) => match value {
-                    Value::String(value) => Ok(Dependency {
-                        version: match Version::new(value) {
-                            Ok(ok) => ok,
-                            Err(error) => return Err(SemverParse { key, error }),
-                        },
-                        name: key,
-                    }),
+                    Value::String(value) => Ok(new_dependency(key, value)),
                     Value::InlineTable(table) => {
+                        if is_sourced_without_version(|k| table.contains_key(k)) {
+                            tracing::debug!(key, "Skipping dependency with no version to minimize");
+                            return Ok(None);
+                        }
                         let Some(value) = table.get_mut("version") else {
                             return Err(MissingVersionKey { key })
                         };
                         match value {
-                            Value::String(value) => Ok(Dependency {
-                                version: match Version::new(value) {
-                                    Ok(ok) => ok,
-                                    Err(error) => return Err(SemverParse { key, error }),
-                                },
-                                name: key,
-                            }),
+                            Value::String(value) => Ok(new_dependency(key, value)),
                             other => Err(UnexpectedVersionType {
                                 key,
                                 ty: value_to_type(other),
@@ -158,7 +208,26 @@ fn fetch_dependencies(
                         ty: value_to_type(other),
                     }),
                 },
-                Item::Table(_) => todo!("support dependency tables"),
+                Item::Table(table) => {
+                    if is_sourced_without_version(|k| table.contains_key(k)) {
+                        tracing::debug!(key, "Skipping dependency with no version to minimize");
+                        return Ok(None);
+                    }
+                    let Some(item) = table.get_mut("version") else {
+                        return Err(MissingVersionKey { key })
+                    };
+                    match item {
+                        Item::Value(Value::String(value)) => Ok(new_dependency(key, value)),
+                        Item::Value(other) => Err(UnexpectedVersionType {
+                            key,
+                            ty: value_to_type(other),
+                        }),
+                        other => Err(UnexpectedVersionType {
+                            key,
+                            ty: item_to_type(other),
+                        }),
+                    }
+                }
                 other => Err(UnexpectedDependencyItem {
                     key,
                     ty: item_to_type(other),
@@ -166,7 +235,7 @@ fn fetch_dependencies(
             }
         })
         .collect();
-    depedencies
+    Ok(depedencies?.into_iter().flatten().collect())
 }
 
 // fn parse_file(file: &mut File) -> Document {
@@ -199,4 +268,125 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn reads_full_dependency_tables() {
+        let mut document: Document = r#"
+            [dependencies]
+            serde = "1.0.160"
+
+            [dependencies.tokio]
+            version = "1.28.0"
+            features = ["full"]
+        "#
+        .parse()
+        .unwrap();
+
+        let dependencies = fetch_dependencies(&mut document, DependencyType::Standard).unwrap();
+        let actual: Vec<_> = dependencies
+            .iter()
+            .map(|d| (d.name.as_str(), d.version.get().clone()))
+            .collect();
+        pretty_assertions::assert_eq!(
+            actual,
+            vec![
+                ("serde", semver::Version::new(1, 0, 160)),
+                ("tokio", semver::Version::new(1, 28, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_git_path_and_workspace_dependencies() {
+        let mut document: Document = r#"
+            [dependencies]
+            serde = { version = "1.0.160" }
+            tokio = { git = "https://github.com/tokio-rs/tokio" }
+            local = { path = "../local" }
+            shared = { workspace = true }
+        "#
+        .parse()
+        .unwrap();
+
+        let dependencies = fetch_dependencies(&mut document, DependencyType::Standard).unwrap();
+        let names: Vec<_> = dependencies.iter().map(|d| d.name.as_str()).collect();
+        pretty_assertions::assert_eq!(names, vec!["serde"]);
+    }
+
+    #[test]
+    fn dependency_type_paths() {
+        pretty_assertions::assert_eq!(DependencyType::Standard.path(), vec!["dependencies"]);
+        pretty_assertions::assert_eq!(DependencyType::Dev.path(), vec!["dev-dependencies"]);
+        pretty_assertions::assert_eq!(DependencyType::Build.path(), vec!["build-dependencies"]);
+        pretty_assertions::assert_eq!(
+            DependencyType::Workspace.path(),
+            vec!["workspace", "dependencies"]
+        );
+        pretty_assertions::assert_eq!(
+            DependencyType::Target {
+                cfg: "cfg(unix)".to_owned()
+            }
+            .path(),
+            vec!["target", "cfg(unix)", "dependencies"]
+        );
+    }
+
+    #[test]
+    fn reads_build_and_workspace_dependencies() {
+        let mut document: Document = r#"
+            [build-dependencies]
+            cc = "1.0.70"
+
+            [workspace.dependencies]
+            serde = "1.0.160"
+        "#
+        .parse()
+        .unwrap();
+
+        let build = fetch_dependencies(&mut document, DependencyType::Build).unwrap();
+        let names: Vec<_> = build.iter().map(|d| d.name.as_str()).collect();
+        pretty_assertions::assert_eq!(names, vec!["cc"]);
+        drop(build);
+
+        let workspace = fetch_dependencies(&mut document, DependencyType::Workspace).unwrap();
+        let names: Vec<_> = workspace.iter().map(|d| d.name.as_str()).collect();
+        pretty_assertions::assert_eq!(names, vec!["serde"]);
+    }
+
+    #[test]
+    fn reads_target_specific_dependencies() {
+        let mut document: Document = r#"
+            [target.'cfg(unix)'.dependencies]
+            libc = "0.2.100"
+        "#
+        .parse()
+        .unwrap();
+
+        let dependencies = fetch_dependencies(
+            &mut document,
+            DependencyType::Target {
+                cfg: "cfg(unix)".to_owned(),
+            },
+        )
+        .unwrap();
+        let names: Vec<_> = dependencies.iter().map(|d| d.name.as_str()).collect();
+        pretty_assertions::assert_eq!(names, vec!["libc"]);
+    }
+
+    #[test]
+    fn skips_non_exact_version_requirements() {
+        let mut document: Document = r#"
+            [dependencies]
+            serde = "1.0.160"
+            tokio = "1"
+            rand = "^0.8"
+            libc = "~0.2.100"
+        "#
+        .parse()
+        .unwrap();
+
+        let dependencies = fetch_dependencies(&mut document, DependencyType::Standard).unwrap();
+        let names: Vec<_> = dependencies.iter().map(|d| d.name.as_str()).collect();
+        pretty_assertions::assert_eq!(names, vec!["serde"]);
+    }
 }
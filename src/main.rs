@@ -4,27 +4,93 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use clap::Parser;
-use toml_edit::Document;
+use clap::{Parser, Subcommand};
+use thiserror::Error;
+use toml_edit::{Document, Item, TomlError, Value};
 
-use crate::dependencies::{fetch_dependencies, DependencyType};
+use crate::{
+    dependencies::{fetch_dependencies, DependencyType, FetchDependenciesError},
+    registry::Registry,
+};
 
 mod dependencies;
+mod registry;
+mod verify;
 
 /// A command-line tool for assigning minimal dependency versions.
 #[derive(Parser)]
 struct Args {
-    /// Path to the crate root.
-    root: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Rewrite a crate's Cargo.toml with minimal dependency versions.
+    Minimize {
+        /// Path to the crate root.
+        root: PathBuf,
+
+        /// Query the crates.io sparse index and use the lowest published
+        /// version satisfying the existing requirement, instead of just
+        /// zeroing minor/patch.
+        #[arg(long)]
+        registry: bool,
+
+        /// Bisect over published versions, actually building the crate, to
+        /// find the true minimum buildable version of each dependency.
+        /// Reports the result without rewriting `Cargo.toml`. Incompatible
+        /// with `--dry-run`: verifying rewrites `Cargo.toml` on disk for
+        /// each build attempt (restoring it before returning), which
+        /// `--dry-run` promises not to do.
+        #[arg(long, conflicts_with = "dry_run")]
+        verify: bool,
+
+        /// In a workspace, only minimize the member whose package name (or,
+        /// failing that, directory name) is this, rather than every member.
+        #[arg(long)]
+        member: Option<String>,
+
+        /// Print the rewritten Cargo.toml to stdout instead of writing it,
+        /// without creating a backup.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Restore Cargo.toml from the Cargo.toml.old backup created by `minimize`.
+    Revert {
+        /// Path to the crate root.
+        root: PathBuf,
+    },
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    TomlParse(#[from] TomlError),
+    #[error(transparent)]
+    FetchDependencies(#[from] FetchDependenciesError),
+    #[error("failed to expand workspace member glob pattern")]
+    GlobPattern(#[from] glob::PatternError),
+    #[error("failed to read workspace member path")]
+    GlobEntry(#[from] glob::GlobError),
 }
 
 const CARGO_TOML: &str = "Cargo.toml";
 const CARGO_TOML_BACKUP: &str = "Cargo.toml.old";
 
+/// Copies `from` to `Cargo.toml.old`, unless a backup already exists. A
+/// second `minimize` run with no edit in between would otherwise overwrite
+/// the backup with the already-minimized manifest, permanently losing the
+/// pristine `Cargo.toml` that `revert` exists to restore.
 fn backup(from: &Path) -> io::Result<()> {
     let mut to = from.to_owned();
     to.pop();
     to.push(CARGO_TOML_BACKUP);
+    if to.exists() {
+        return Ok(());
+    }
     fs::copy(from, to).map(|_| ())
 }
 
@@ -35,40 +101,304 @@ fn revert(to: &Path) -> io::Result<()> {
     fs::copy(from, to).map(|_| ())
 }
 
-fn main() {
-    let Args { root } = Args::parse();
+/// The `cfg(...)` keys under `[target]`, e.g. `target.'cfg(unix)'.dependencies`.
+fn target_cfgs(document: &Document) -> Vec<String> {
+    document
+        .get("target")
+        .and_then(Item::as_table)
+        .map(|table| table.iter().map(|(cfg, _)| cfg.to_owned()).collect())
+        .unwrap_or_default()
+}
+
+/// Every dependency group `document` has, in the order `minimize` visits
+/// them.
+fn all_dependency_types(document: &Document) -> Vec<DependencyType> {
+    [
+        DependencyType::Standard,
+        DependencyType::Dev,
+        DependencyType::Build,
+        DependencyType::Workspace,
+    ]
+    .into_iter()
+    .chain(
+        target_cfgs(document)
+            .into_iter()
+            .map(|cfg| DependencyType::Target { cfg }),
+    )
+    .collect()
+}
+
+/// The crate roots `minimize` should process: the `[workspace.members]` of
+/// `root`'s manifest (glob patterns like `crates/*` included), plus `root`
+/// itself if its manifest also has a `[package]` or a `[workspace.dependencies]`
+/// table of its own to minimize. A manifest with no `[workspace]` at all just
+/// yields `root`.
+fn manifest_roots(root: &Path, document: &Document) -> Result<Vec<PathBuf>, Error> {
+    let workspace = document.get("workspace").and_then(Item::as_table);
 
-    tracing::info!(path = %root.display(), "Starting dependency minimizing...");
+    let members = workspace
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(Item::as_array);
 
-    let mut root_toml_path = root;
+    let mut roots = Vec::new();
+    if let Some(members) = members {
+        for member in members.iter().filter_map(Value::as_str) {
+            let pattern = root.join(member);
+            for entry in glob::glob(&pattern.to_string_lossy())? {
+                let path = entry?;
+                if path.is_dir() {
+                    roots.push(path);
+                }
+            }
+        }
+    }
+
+    let root_has_own_dependencies = document.get("package").is_some()
+        || workspace.is_some_and(|workspace| workspace.get("dependencies").is_some());
+    if roots.is_empty() || root_has_own_dependencies {
+        roots.insert(0, root.to_owned());
+    }
+    Ok(roots)
+}
+
+/// The `[package].name` declared in the `Cargo.toml` at `root`, if any.
+fn package_name(root: &Path) -> Result<Option<String>, Error> {
+    let mut path = root.to_owned();
+    path.push(CARGO_TOML);
+    let raw = fs::read_to_string(path)?;
+    let document: Document = raw.parse()?;
+    Ok(document
+        .get("package")
+        .and_then(Item::as_table)
+        .and_then(|package| package.get("name"))
+        .and_then(Item::as_str)
+        .map(str::to_owned))
+}
+
+/// Keeps only the `roots` whose crate name (preferred) or directory name
+/// matches `member`, mirroring `cargo`'s own `-p`/`--package` convention of
+/// selecting by package name rather than directory.
+fn filter_by_member(roots: Vec<PathBuf>, member: &str) -> Result<Vec<PathBuf>, Error> {
+    roots
+        .into_iter()
+        .filter_map(|path| match package_name(&path) {
+            Ok(name) if name.as_deref() == Some(member) => Some(Ok(path)),
+            Ok(_) if path.file_name().is_some_and(|name| name == member) => Some(Ok(path)),
+            Ok(_) => None,
+            Err(error) => Some(Err(error)),
+        })
+        .collect()
+}
+
+/// Runs the backup/minimize/rewrite (or `--verify`) pipeline on the
+/// `Cargo.toml` in `root`.
+fn minimize(
+    root: &Path,
+    use_registry: bool,
+    verify: bool,
+    dry_run: bool,
+) -> Result<(), Error> {
+    let mut root_toml_path = root.to_owned();
     root_toml_path.push(CARGO_TOML);
 
-    tracing::info!(toml = %root_toml_path.display(), "Creating a backup...");
-    backup(&root_toml_path).expect("TODO");
+    if !dry_run {
+        tracing::info!(toml = %root_toml_path.display(), "Creating a backup...");
+        backup(&root_toml_path)?;
+    }
 
     let mut root_toml = fs::OpenOptions::new()
         .read(true)
         .write(true)
-        .open(&root_toml_path)
-        .expect("TODO");
+        .open(&root_toml_path)?;
     let mut raw = String::new();
-    root_toml.read_to_string(&mut raw).expect("TODO");
-
-    let mut document = raw.parse().expect("TODO");
-    let dependencies = fetch_dependencies(&mut document, DependencyType::Standard).expect("TODO");
-    for mut dependency in dependencies {
-        let version = dependency.version.get_mut();
-        if version.major == 0 {
-            version.patch = 0;
-        } else {
-            version.minor = 0;
-            version.patch = 0;
+    root_toml.read_to_string(&mut raw)?;
+
+    let mut document: Document = raw.parse()?;
+    let dependency_types = all_dependency_types(&document);
+
+    if verify {
+        let minimized = self::verify::verify(root, &root_toml_path, dependency_types)?;
+        for self::verify::Minimized { name, version } in minimized {
+            tracing::info!(name, %version, "Found verified minimum version");
+        }
+        return Ok(());
+    }
+
+    let mut registry = Registry::new();
+
+    for ty in dependency_types {
+        let dependencies = match fetch_dependencies(&mut document, ty) {
+            Ok(dependencies) => dependencies,
+            Err(FetchDependenciesError::MissingDependencyItem) => continue,
+            Err(error) => return Err(error.into()),
+        };
+        for mut dependency in dependencies {
+            let published = use_registry
+                .then(|| {
+                    registry.minimum_published_version(
+                        &dependency.name,
+                        dependency.version.requirement(),
+                    )
+                })
+                .flatten();
+
+            let version = published.unwrap_or_else(|| {
+                let mut version = dependency.version.get().clone();
+                if version.major == 0 {
+                    version.patch = 0;
+                } else {
+                    version.minor = 0;
+                    version.patch = 0;
+                }
+                version
+            });
+            dependency.version.set(version);
+        }
+    }
+
+    if dry_run {
+        print!("{document}");
+        return Ok(());
+    }
+
+    root_toml.set_len(0)?;
+    root_toml.rewind()?;
+    root_toml.write_all(document.to_string().as_bytes())?;
+    Ok(())
+}
+
+fn main() -> Result<(), Error> {
+    let Args { command } = Args::parse();
+
+    match command {
+        Command::Minimize {
+            root,
+            registry,
+            verify,
+            member,
+            dry_run,
+        } => {
+            tracing::info!(path = %root.display(), "Starting dependency minimizing...");
+
+            let mut root_toml_path = root.clone();
+            root_toml_path.push(CARGO_TOML);
+            let raw = fs::read_to_string(&root_toml_path)?;
+            let document: Document = raw.parse()?;
+
+            let mut roots = manifest_roots(&root, &document)?;
+            if let Some(member) = &member {
+                roots = filter_by_member(roots, member)?;
+            }
+
+            for root in roots {
+                minimize(&root, registry, verify, dry_run)?;
+            }
+            Ok(())
+        }
+        Command::Revert { root } => {
+            let mut root_toml_path = root;
+            root_toml_path.push(CARGO_TOML);
+
+            tracing::info!(toml = %root_toml_path.display(), "Reverting from backup...");
+            revert(&root_toml_path)?;
+            Ok(())
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn target_cfgs_lists_cfg_keys() {
+        let document: Document = r#"
+            [target.'cfg(unix)'.dependencies]
+            libc = "0.2.100"
 
-    root_toml.set_len(0).expect("TODO");
-    root_toml.rewind().expect("TODO");
-    root_toml
-        .write_all(document.to_string().as_bytes())
-        .expect("TODO");
+            [target.'cfg(windows)'.dependencies]
+            winapi = "0.3.9"
+        "#
+        .parse()
+        .unwrap();
+
+        let mut cfgs = target_cfgs(&document);
+        cfgs.sort();
+        pretty_assertions::assert_eq!(cfgs, vec!["cfg(unix)", "cfg(windows)"]);
+    }
+
+    #[test]
+    fn target_cfgs_empty_without_target_table() {
+        let document: Document = "[dependencies]\nserde = \"1.0.160\"\n".parse().unwrap();
+        pretty_assertions::assert_eq!(target_cfgs(&document), Vec::<String>::new());
+    }
+
+    /// A fresh, empty directory under the OS temp dir, unique per call so
+    /// parallel tests don't collide.
+    fn temp_dir(label: &str) -> PathBuf {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "cargo-min-test-{label}-{}-{n}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn manifest_roots_resolves_glob_members() {
+        let dir = temp_dir("glob-members");
+        fs::create_dir_all(dir.join("crates/a")).unwrap();
+        fs::create_dir_all(dir.join("crates/b")).unwrap();
+
+        let document: Document = r#"
+            [workspace]
+            members = ["crates/*"]
+        "#
+        .parse()
+        .unwrap();
+
+        let mut roots = manifest_roots(&dir, &document).unwrap();
+        roots.sort();
+        let mut expected = vec![dir.join("crates/a"), dir.join("crates/b")];
+        expected.sort();
+        pretty_assertions::assert_eq!(roots, expected);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn manifest_roots_includes_root_alongside_members_when_workspace_has_own_dependencies() {
+        let dir = temp_dir("workspace-deps");
+        fs::create_dir_all(dir.join("crates/a")).unwrap();
+
+        let document: Document = r#"
+            [workspace]
+            members = ["crates/*"]
+
+            [workspace.dependencies]
+            serde = "1.0.160"
+        "#
+        .parse()
+        .unwrap();
+
+        let roots = manifest_roots(&dir, &document).unwrap();
+        pretty_assertions::assert_eq!(roots, vec![dir.clone(), dir.join("crates/a")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn manifest_roots_without_workspace_yields_just_root() {
+        let dir = temp_dir("no-workspace");
+        let document: Document = "[package]\nname = \"foo\"\n".parse().unwrap();
+
+        let roots = manifest_roots(&dir, &document).unwrap();
+        pretty_assertions::assert_eq!(roots, vec![dir.clone()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use thiserror::Error;
+
+const INDEX_BASE: &str = "https://index.crates.io";
+
+/// A single line of a crates.io sparse index response.
+#[derive(Debug, Deserialize)]
+struct IndexVersion {
+    vers: Version,
+    #[serde(default)]
+    yanked: bool,
+}
+
+#[derive(Debug, Error)]
+enum RegistryError {
+    #[error("failed to fetch index for \"{name}\"")]
+    Request {
+        name: String,
+        #[source]
+        error: Box<ureq::Error>,
+    },
+    #[error("failed to read index response for \"{name}\"")]
+    Io {
+        name: String,
+        #[source]
+        error: std::io::Error,
+    },
+    #[error("failed to parse index entry for \"{name}\"")]
+    Parse {
+        name: String,
+        #[source]
+        error: serde_json::Error,
+    },
+}
+
+/// The lowercase path segments crates.io shards its sparse index by, e.g.
+/// `"serde"` -> `"se/rd/serde"`.
+fn index_path(name: &str) -> String {
+    let lower = name.to_lowercase();
+    match lower.len() {
+        1 => format!("1/{lower}"),
+        2 => format!("2/{lower}"),
+        3 => format!("3/{}/{lower}", &lower[..1]),
+        _ => format!("{}/{}/{lower}", &lower[..2], &lower[2..4]),
+    }
+}
+
+fn fetch_versions(name: &str) -> Result<Vec<Version>, RegistryError> {
+    let url = format!("{INDEX_BASE}/{}", index_path(name));
+    let body = ureq::get(&url)
+        .call()
+        .map_err(|error| RegistryError::Request {
+            name: name.to_owned(),
+            error: Box::new(error),
+        })?
+        .into_string()
+        .map_err(|error| RegistryError::Io {
+            name: name.to_owned(),
+            error,
+        })?;
+
+    let mut versions = Vec::new();
+    for line in body.lines().filter(|line| !line.is_empty()) {
+        let entry: IndexVersion =
+            serde_json::from_str(line).map_err(|error| RegistryError::Parse {
+                name: name.to_owned(),
+                error,
+            })?;
+        if !entry.yanked {
+            versions.push(entry.vers);
+        }
+    }
+    versions.sort();
+    Ok(versions)
+}
+
+/// Queries the crates.io sparse index for published versions, caching one
+/// response per crate name across lookups.
+pub(crate) struct Registry {
+    cache: HashMap<String, Vec<Version>>,
+}
+
+impl Registry {
+    pub(crate) fn new() -> Self {
+        Self {
+            cache: HashMap::new(),
+        }
+    }
+
+    fn published_versions(&mut self, name: &str) -> Result<&[Version], RegistryError> {
+        if !self.cache.contains_key(name) {
+            let versions = fetch_versions(name)?;
+            self.cache.insert(name.to_owned(), versions);
+        }
+        Ok(&self.cache[name])
+    }
+
+    /// Returns every published, non-yanked version of `name` that satisfies
+    /// `requirement` (the literal requirement string from `Cargo.toml`,
+    /// e.g. `"~1.2"` or `"1"`), sorted ascending, or `None` if the
+    /// requirement can't be parsed or the registry is unavailable.
+    pub(crate) fn satisfying_versions(
+        &mut self,
+        name: &str,
+        requirement: &str,
+    ) -> Option<Vec<Version>> {
+        let req = VersionReq::parse(requirement).ok()?;
+        let versions = self.published_versions(name).ok()?;
+        Some(
+            versions
+                .iter()
+                .filter(|version| req.matches(version))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Returns the lowest published, non-yanked version of `name` that still
+    /// satisfies `requirement`, or `None` if the registry is unavailable or
+    /// no published version satisfies it.
+    pub(crate) fn minimum_published_version(
+        &mut self,
+        name: &str,
+        requirement: &str,
+    ) -> Option<Version> {
+        self.satisfying_versions(name, requirement)?.into_iter().next()
+    }
+}
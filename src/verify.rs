@@ -0,0 +1,168 @@
+use std::{fs, path::Path, process::Command};
+
+use toml_edit::Document;
+
+use crate::{
+    dependencies::{fetch_dependencies, DependencyType, FetchDependenciesError},
+    registry::Registry,
+    Error,
+};
+
+/// One dependency's bisected minimum buildable version.
+#[derive(Debug)]
+pub(crate) struct Minimized {
+    pub(crate) name: String,
+    pub(crate) version: semver::Version,
+}
+
+/// Rewrites `path` with `name`'s version set to `candidate` and runs `cargo
+/// build` in `root`, reporting whether it succeeded.
+fn try_build(
+    root: &Path,
+    path: &Path,
+    ty: DependencyType,
+    name: &str,
+    candidate: &semver::Version,
+) -> Result<bool, Error> {
+    let raw = fs::read_to_string(path)?;
+    let mut document: Document = raw.parse()?;
+    let dependencies = fetch_dependencies(&mut document, ty)?;
+    for mut dependency in dependencies {
+        if dependency.name == name {
+            dependency.version.set(candidate.clone());
+        }
+    }
+    fs::write(path, document.to_string())?;
+
+    let status = Command::new("cargo")
+        .arg("build")
+        .current_dir(root)
+        .status()?;
+    Ok(status.success())
+}
+
+/// Binary-searches indices `0..len`, assumed monotonic under `success` (once
+/// true for some index, true for every higher index), for the lowest index
+/// where `success` returns `true`. Returns `None` if `success` is never true.
+fn bisect_index<E>(
+    len: usize,
+    mut success: impl FnMut(usize) -> Result<bool, E>,
+) -> Result<Option<usize>, E> {
+    let mut lo = 0;
+    let mut hi = len;
+    let mut minimum = None;
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if success(mid)? {
+            minimum = Some(mid);
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    Ok(minimum)
+}
+
+/// Binary-searches `candidates` (sorted ascending) for the lowest version
+/// that still lets the crate build. Build success is assumed monotonic in
+/// version for a single dependency, so this converges in `O(log n)` builds.
+fn bisect(
+    root: &Path,
+    path: &Path,
+    ty: DependencyType,
+    name: &str,
+    candidates: &[semver::Version],
+) -> Result<Option<semver::Version>, Error> {
+    let index = bisect_index(candidates.len(), |mid| {
+        try_build(root, path, ty.clone(), name, &candidates[mid])
+    })?;
+    Ok(index.map(|i| candidates[i].clone()))
+}
+
+/// Finds the real minimum buildable version of every dependency across
+/// `dependency_types` by bisecting over its published versions. `path` is
+/// restored to the contents it had on entry before each independent
+/// dependency search, and once more before returning (also on error), so the
+/// manifest on disk is left untouched.
+pub(crate) fn verify(
+    root: &Path,
+    path: &Path,
+    dependency_types: Vec<DependencyType>,
+) -> Result<Vec<Minimized>, Error> {
+    let original = fs::read_to_string(path)?;
+    let result = verify_from(root, path, dependency_types, &original);
+    fs::write(path, &original)?;
+    result
+}
+
+fn verify_from(
+    root: &Path,
+    path: &Path,
+    dependency_types: Vec<DependencyType>,
+    original: &str,
+) -> Result<Vec<Minimized>, Error> {
+    let mut registry = Registry::new();
+    let mut minimized = Vec::new();
+
+    for ty in dependency_types {
+        fs::write(path, original)?;
+        let mut document: Document = original.parse()?;
+        let names_and_requirements: Vec<(String, String)> =
+            match fetch_dependencies(&mut document, ty.clone()) {
+                Ok(dependencies) => dependencies
+                    .iter()
+                    .map(|dependency| {
+                        (
+                            dependency.name.clone(),
+                            dependency.version.requirement().to_owned(),
+                        )
+                    })
+                    .collect(),
+                Err(FetchDependenciesError::MissingDependencyItem) => continue,
+                Err(error) => return Err(error.into()),
+            };
+
+        for (name, requirement) in names_and_requirements {
+            let Some(candidates) = registry.satisfying_versions(&name, &requirement) else {
+                continue;
+            };
+            fs::write(path, original)?;
+            if let Some(version) = bisect(root, path, ty.clone(), &name, &candidates)? {
+                minimized.push(Minimized { name, version });
+            }
+        }
+    }
+
+    Ok(minimized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    #[test]
+    fn bisect_index_finds_leftmost_true() {
+        let threshold = 4;
+        let result = bisect_index::<Infallible>(10, |i| Ok(i >= threshold)).unwrap();
+        pretty_assertions::assert_eq!(result, Some(threshold));
+    }
+
+    #[test]
+    fn bisect_index_returns_none_when_never_true() {
+        let result = bisect_index::<Infallible>(5, |_| Ok(false)).unwrap();
+        pretty_assertions::assert_eq!(result, None);
+    }
+
+    #[test]
+    fn bisect_index_returns_zero_when_always_true() {
+        let result = bisect_index::<Infallible>(5, |_| Ok(true)).unwrap();
+        pretty_assertions::assert_eq!(result, Some(0));
+    }
+
+    #[test]
+    fn bisect_index_on_empty_range_returns_none() {
+        let result = bisect_index::<Infallible>(0, |_| Ok(true)).unwrap();
+        pretty_assertions::assert_eq!(result, None);
+    }
+}